@@ -0,0 +1,79 @@
+use crate::task::{keyboard, timer};
+use lazy_static::lazy_static;
+use pic8259::ChainedPics;
+use spin::Mutex;
+use x86_64::structures::idt::InterruptDescriptorTable;
+
+/// The master PIC is remapped to start at 32, since the first 32 interrupt vectors are
+/// reserved for CPU exceptions.
+pub const PIC_1_OFFSET: u8 = 32;
+pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
+
+pub static PICS: Mutex<ChainedPics> =
+  Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum InterruptIndex {
+  Timer = PIC_1_OFFSET,
+  Keyboard,
+}
+
+impl InterruptIndex {
+  fn as_u8(self) -> u8 {
+    self as u8
+  }
+
+  fn as_usize(self) -> usize {
+    usize::from(self.as_u8())
+  }
+}
+
+lazy_static! {
+  static ref IDT: InterruptDescriptorTable = {
+    let mut idt = InterruptDescriptorTable::new();
+    idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer_interrupt_handler);
+    idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
+    idt
+  };
+}
+
+pub fn init_idt() {
+  IDT.load();
+}
+
+/// Brings up the PICs and programs the PIT, then re-enables interrupts. Must run once,
+/// before any code awaits a `Timer` or reads from the keyboard stream.
+pub fn init() {
+  unsafe { PICS.lock().initialize() };
+  timer::init_pit(100);
+
+  init_idt();
+  x86_64::instructions::interrupts::enable();
+}
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
+  timer::on_tick();
+
+  unsafe {
+    PICS
+      .lock()
+      .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
+  }
+}
+
+extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
+  use x86_64::instructions::port::Port;
+
+  let mut port: Port<u8> = Port::new(0x60);
+  let scancode: u8 = unsafe { port.read() };
+  keyboard::add_scancode(scancode);
+
+  unsafe {
+    PICS
+      .lock()
+      .notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
+  }
+}
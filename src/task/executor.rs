@@ -0,0 +1,123 @@
+// A low-power executor modeled on embassy: tasks only get polled once something has
+// actually woken them, and the CPU halts on `hlt` whenever there's nothing ready, instead of
+// `SimpleExecutor`'s busy spin.
+
+use super::{Task, TaskId};
+use crate::println;
+use alloc::{collections::BTreeMap, sync::Arc, task::Wake};
+use core::task::{Context, Poll, Waker};
+use crossbeam_queue::ArrayQueue;
+
+pub struct Executor {
+  tasks: BTreeMap<TaskId, Task>,
+  task_queue: Arc<ArrayQueue<TaskId>>,
+  waker_cache: BTreeMap<TaskId, Waker>,
+}
+
+impl Executor {
+  pub fn new() -> Self {
+    Executor {
+      tasks: BTreeMap::new(),
+      task_queue: Arc::new(ArrayQueue::new(100)),
+      waker_cache: BTreeMap::new(),
+    }
+  }
+
+  pub fn spawn(&mut self, task: Task) {
+    let task_id = task.id;
+    if self.tasks.insert(task_id, task).is_some() {
+      panic!("task with same ID already in tasks");
+    }
+
+    // Mirrors keyboard::add_scancode: a full ready queue is a transient condition (plausibly
+    // hit if a single timer tick wakes many tasks at once), not a reason to bring down the
+    // kernel. The task stays in `tasks` and simply won't run until it's woken again.
+    if self.task_queue.push(task_id).is_err() {
+      println!("WARNING: executor ready queue full; dropping spawn wakeup for {:?}", task_id);
+    }
+  }
+
+  pub fn run(&mut self) -> ! {
+    loop {
+      self.run_ready_tasks();
+      self.sleep_if_idle();
+    }
+  }
+
+  fn run_ready_tasks(&mut self) {
+    let Self {
+      tasks,
+      task_queue,
+      waker_cache,
+    } = self;
+
+    while let Ok(task_id) = task_queue.pop() {
+      let task = match tasks.get_mut(&task_id) {
+        Some(task) => task,
+        None => continue, // task no longer exists
+      };
+
+      let waker = waker_cache
+        .entry(task_id)
+        .or_insert_with(|| TaskWaker::new(task_id, task_queue.clone()));
+      let mut context = Context::from_waker(waker);
+
+      match task.poll(&mut context) {
+        Poll::Ready(()) => {
+          tasks.remove(&task_id);
+          waker_cache.remove(&task_id);
+        }
+        Poll::Pending => {}
+      }
+    }
+  }
+
+  // Disabling interrupts before the emptiness check closes the race where an interrupt
+  // wakes a task between the check and the `hlt`, which would otherwise halt the CPU with
+  // a ready task sitting in the queue and nothing left to wake it back up.
+  fn sleep_if_idle(&self) {
+    use x86_64::instructions::interrupts::{self, enable_and_hlt};
+
+    interrupts::disable();
+    if self.task_queue.is_empty() {
+      enable_and_hlt();
+    } else {
+      interrupts::enable();
+    }
+  }
+}
+
+struct TaskWaker {
+  task_id: TaskId,
+  task_queue: Arc<ArrayQueue<TaskId>>,
+}
+
+impl TaskWaker {
+  fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
+    Waker::from(Arc::new(TaskWaker {
+      task_id,
+      task_queue,
+    }))
+  }
+
+  fn wake_task(&self) {
+    // As in spawn: drop and log rather than panic. Losing a wakeup on a full queue just
+    // leaves the task pending until something else wakes it again.
+    if self.task_queue.push(self.task_id).is_err() {
+      println!(
+        "WARNING: executor ready queue full; dropping wakeup for task {:?}",
+        self.task_id
+      );
+    }
+  }
+}
+
+impl Wake for TaskWaker {
+  fn wake(self: Arc<Self>) {
+    self.wake_task();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.wake_task();
+  }
+}
@@ -61,26 +61,171 @@ impl Stream for ScancodeStream {
   }
 }
 
+use crate::vga_buffer::WRITER;
 use futures_util::{
   stream::{Stream, StreamExt},
   task::AtomicWaker,
 };
-use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1};
+use pc_keyboard::{layouts, DecodedKey, HandleControl, KeyCode, Keyboard, ScancodeSet1};
+use spin::Mutex;
 
 static WAKER: AtomicWaker = AtomicWaker::new();
 
+// How many rows PageUp/PageDown move the VGA scrollback window by, one screen minus one
+// line of overlap so context carries across the page break.
+const SCROLL_STEP: usize = 24;
+
+/// The `pc_keyboard` layout a `KeyEventStream` decodes scancodes with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+  Us104Key,
+  Dvorak104Key,
+}
+
+// The shift/ctrl/alt/caps-lock/num-lock state held by `Modifiers` doesn't depend on which
+// layout is active, but `Keyboard<L, S>` is generic over the layout, so swapping layouts at
+// runtime means swapping out the whole `Keyboard`, not just its `L` parameter. Held modifier
+// state is reset by the swap.
+enum LayoutKeyboard {
+  Us104Key(Keyboard<layouts::Us104Key, ScancodeSet1>),
+  Dvorak104Key(Keyboard<layouts::Dvorak104Key, ScancodeSet1>),
+}
+
+impl LayoutKeyboard {
+  fn new(layout: Layout) -> Self {
+    match layout {
+      Layout::Us104Key => LayoutKeyboard::Us104Key(Keyboard::new(
+        layouts::Us104Key,
+        ScancodeSet1,
+        HandleControl::Ignore,
+      )),
+      Layout::Dvorak104Key => LayoutKeyboard::Dvorak104Key(Keyboard::new(
+        layouts::Dvorak104Key,
+        ScancodeSet1,
+        HandleControl::Ignore,
+      )),
+    }
+  }
+
+  fn add_byte(&mut self, scancode: u8) -> Option<KeyEvent> {
+    match self {
+      LayoutKeyboard::Us104Key(keyboard) => decode(keyboard, scancode),
+      LayoutKeyboard::Dvorak104Key(keyboard) => decode(keyboard, scancode),
+    }
+  }
+}
+
+fn decode<L: pc_keyboard::KeyboardLayout>(
+  keyboard: &mut Keyboard<L, ScancodeSet1>,
+  scancode: u8,
+) -> Option<KeyEvent> {
+  let key_event = keyboard.add_byte(scancode).ok().flatten()?;
+  let key = keyboard.process_keyevent(key_event)?;
+
+  Some(KeyEvent {
+    key,
+    modifiers: Modifiers::from(keyboard.get_modifiers()),
+  })
+}
+
+/// Shift/ctrl/alt/caps-lock/num-lock state, flattened from `pc_keyboard`'s left/right keys.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Modifiers {
+  pub shift: bool,
+  pub ctrl: bool,
+  pub alt: bool,
+  pub caps_lock: bool,
+  pub num_lock: bool,
+}
+
+impl From<&pc_keyboard::Modifiers> for Modifiers {
+  fn from(modifiers: &pc_keyboard::Modifiers) -> Self {
+    Modifiers {
+      shift: modifiers.lshift || modifiers.rshift,
+      ctrl: modifiers.lctrl || modifiers.rctrl,
+      alt: modifiers.lalt || modifiers.ralt,
+      caps_lock: modifiers.capslock,
+      num_lock: modifiers.numlock,
+    }
+  }
+}
+
+#[test_case]
+fn test_modifiers_from_flattens_left_and_right_keys() {
+  let mut raw = pc_keyboard::Modifiers::default();
+  raw.rshift = true;
+  raw.lctrl = true;
+
+  let modifiers = Modifiers::from(&raw);
+  assert!(modifiers.shift, "rshift should flatten into shift");
+  assert!(modifiers.ctrl, "lctrl should flatten into ctrl");
+  assert!(!modifiers.alt);
+  assert!(!modifiers.caps_lock);
+  assert!(!modifiers.num_lock);
+}
+
+/// A decoded key together with the modifier keys held when it was produced, letting callers
+/// build chord handling (e.g. Ctrl-C) instead of only seeing raw characters.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyEvent {
+  pub key: DecodedKey,
+  pub modifiers: Modifiers,
+}
+
+/// A higher-level async stream of structured key events, layered on top of `ScancodeStream`.
+/// The active layout can be swapped at runtime with `set_layout` without reconstructing the
+/// stream (and therefore without losing any scancodes already queued).
+pub struct KeyEventStream {
+  scancodes: ScancodeStream,
+  keyboard: Mutex<LayoutKeyboard>,
+}
+
+impl KeyEventStream {
+  pub fn new(layout: Layout) -> Self {
+    KeyEventStream {
+      scancodes: ScancodeStream::new(),
+      keyboard: Mutex::new(LayoutKeyboard::new(layout)),
+    }
+  }
+
+  pub fn set_layout(&self, layout: Layout) {
+    *self.keyboard.lock() = LayoutKeyboard::new(layout);
+  }
+}
+
+impl Stream for KeyEventStream {
+  type Item = KeyEvent;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<KeyEvent>> {
+    let this = self.get_mut();
+
+    loop {
+      let scancode = match Pin::new(&mut this.scancodes).poll_next(cx) {
+        Poll::Ready(Some(scancode)) => scancode,
+        Poll::Ready(None) => return Poll::Ready(None),
+        Poll::Pending => return Poll::Pending,
+      };
+
+      if let Some(event) = this.keyboard.lock().add_byte(scancode) {
+        return Poll::Ready(Some(event));
+      }
+    }
+  }
+}
+
 pub async fn print_keypresses() {
-  let mut scancodes = ScancodeStream::new();
-  let mut keyboard = Keyboard::new(layouts::Us104Key, ScancodeSet1, HandleControl::Ignore);
-
-  while let Some(scancode) = scancodes.next().await {
-    if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-      if let Some(key) = keyboard.process_keyevent(key_event) {
-        match key {
-          DecodedKey::Unicode(char) => print!("{}", char),
-          DecodedKey::RawKey(key) => print!("{:?}", key),
-        }
+  let mut events = KeyEventStream::new(Layout::Us104Key);
+
+  while let Some(event) = events.next().await {
+    match event.key {
+      DecodedKey::Unicode(char) => print!("{}", char),
+      DecodedKey::RawKey(KeyCode::PageUp) => x86_64::instructions::interrupts::without_interrupts(
+        || WRITER.lock().scroll_up(SCROLL_STEP),
+      ),
+      DecodedKey::RawKey(KeyCode::PageDown) => {
+        x86_64::instructions::interrupts::without_interrupts(|| WRITER.lock().scroll_down(SCROLL_STEP))
       }
+      DecodedKey::RawKey(key) => print!("{:?}", key),
     }
   }
 }
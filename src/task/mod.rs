@@ -0,0 +1,40 @@
+use alloc::boxed::Box;
+use core::{
+  future::Future,
+  pin::Pin,
+  sync::atomic::{AtomicU64, Ordering},
+  task::{Context, Poll},
+};
+
+pub mod executor;
+pub mod keyboard;
+pub mod simple_executor;
+pub mod timer;
+
+pub struct Task {
+  id: TaskId,
+  future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+impl Task {
+  pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+    Task {
+      id: TaskId::new(),
+      future: Box::pin(future),
+    }
+  }
+
+  fn poll(&mut self, context: &mut Context) -> Poll<()> {
+    self.future.as_mut().poll(context)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct TaskId(u64);
+
+impl TaskId {
+  fn new() -> Self {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+  }
+}
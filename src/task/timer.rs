@@ -0,0 +1,133 @@
+// A timer queue modeled on embassy's integrated timer: the timer interrupt bumps a tick
+// counter and wakes every `Timer` whose deadline has passed, so tasks can `Timer::after(...)`
+// or `sleep(...)` instead of busy-polling for I/O.
+
+use alloc::collections::BinaryHeap;
+use core::{
+  cmp::Reverse,
+  future::Future,
+  pin::Pin,
+  sync::atomic::{AtomicU64, Ordering},
+  task::{Context, Poll, Waker},
+};
+use spin::Mutex;
+use x86_64::instructions::{interrupts, port::Port};
+
+/// Base frequency (Hz) of the PIT's internal oscillator.
+const PIT_FREQUENCY: u32 = 1_193_182;
+
+/// Monotonic tick counter, incremented once per timer interrupt.
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Wakers waiting for a tick, ordered so the earliest deadline is popped first.
+static TIMER_QUEUE: Mutex<BinaryHeap<Reverse<QueuedWaker>>> = Mutex::new(BinaryHeap::new());
+
+struct QueuedWaker {
+  wake_at_tick: u64,
+  waker: Waker,
+}
+
+impl PartialEq for QueuedWaker {
+  fn eq(&self, other: &Self) -> bool {
+    self.wake_at_tick == other.wake_at_tick
+  }
+}
+
+impl Eq for QueuedWaker {}
+
+impl PartialOrd for QueuedWaker {
+  fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for QueuedWaker {
+  fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+    self.wake_at_tick.cmp(&other.wake_at_tick)
+  }
+}
+
+/// Programs PIT channel 0 (mode 3, square wave) to fire at approximately `frequency_hz`.
+pub fn init_pit(frequency_hz: u32) {
+  let divisor = (PIT_FREQUENCY / frequency_hz) as u16;
+
+  let mut command: Port<u8> = Port::new(0x43);
+  let mut channel0: Port<u8> = Port::new(0x40);
+
+  unsafe {
+    command.write(0x36u8);
+    channel0.write((divisor & 0xff) as u8);
+    channel0.write((divisor >> 8) as u8);
+  }
+}
+
+/// Returns the number of timer interrupts that have fired so far.
+pub fn ticks() -> u64 {
+  TICKS.load(Ordering::Relaxed)
+}
+
+// Called by the timer interrupt handler. Must not allocate or block: it only pops from the
+// BinaryHeap, which never allocates (pushes happen in `Timer::poll`), and wake() only queues
+// the task for the executor, it doesn't poll it.
+pub(crate) fn on_tick() {
+  let now = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+
+  let mut queue = TIMER_QUEUE.lock();
+  while let Some(Reverse(next)) = queue.peek() {
+    if next.wake_at_tick > now {
+      break;
+    }
+
+    let Reverse(next) = queue.pop().unwrap();
+    next.waker.wake();
+  }
+}
+
+/// A future that becomes ready once at least `ticks` timer interrupts have elapsed.
+pub struct Timer {
+  deadline: u64,
+}
+
+impl Timer {
+  pub fn after(ticks: u64) -> Self {
+    Timer {
+      deadline: self::ticks().saturating_add(ticks),
+    }
+  }
+}
+
+impl Future for Timer {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+    if ticks() >= self.deadline {
+      return Poll::Ready(());
+    }
+
+    // Disable interrupts around the recheck-then-register so a tick landing between the
+    // check above and the registration below can't be missed, which would otherwise leave
+    // this task asleep forever.
+    let ready = interrupts::without_interrupts(|| {
+      if ticks() >= self.deadline {
+        true
+      } else {
+        TIMER_QUEUE.lock().push(Reverse(QueuedWaker {
+          wake_at_tick: self.deadline,
+          waker: cx.waker().clone(),
+        }));
+        false
+      }
+    });
+
+    if ready {
+      Poll::Ready(())
+    } else {
+      Poll::Pending
+    }
+  }
+}
+
+/// Suspends the calling task until `ticks` timer interrupts have elapsed.
+pub async fn sleep(ticks: u64) {
+  Timer::after(ticks).await
+}
@@ -1,3 +1,5 @@
+mod cp437;
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)] // Enable copy semantics for the type and make it printable and comparable.
 #[repr(u8)]
@@ -22,13 +24,25 @@ pub enum Color {
 
 ///////////////////////////////////////////////////////////
 
+// The VGA attribute byte packs: bits 0-3 foreground, bits 4-6 background, bit 7 blink.
+// `background` is therefore masked to its low 3 bits, one of the eight non-bright colors
+// Black..LightGray: a bright color (bit 3 set) becomes its dark counterpart instead of being
+// read as the blink bit.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)] // Ensure that it has the same memory layout as its single field.
 struct ColorCode(u8);
 
 impl ColorCode {
   fn new(foreground: Color, background: Color) -> ColorCode {
-    ColorCode((background as u8) << 4 | (foreground as u8))
+    ColorCode(((background as u8) & 0x7) << 4 | (foreground as u8))
+  }
+
+  fn with_blink(self, blink: bool) -> ColorCode {
+    if blink {
+      ColorCode(self.0 | 0x80)
+    } else {
+      ColorCode(self.0 & !0x80)
+    }
   }
 }
 
@@ -44,6 +58,27 @@ struct ScreenChar {
 const BUFFER_HEIGHT: usize = 25;
 const BUFFER_WIDTH: usize = 80;
 
+// How many rows of scrollback the off-screen history keeps, including the rows currently
+// on screen. Older rows are dropped once the ring buffer is full.
+const HISTORY_ROWS: usize = 500;
+
+#[derive(Debug, Clone, Copy)]
+struct Row {
+  chars: [ScreenChar; BUFFER_WIDTH],
+}
+
+impl Row {
+  fn blank(color_code: ColorCode) -> Row {
+    Row {
+      chars: [ScreenChar {
+        ascii_character: b' ',
+        color_code,
+      }; BUFFER_WIDTH],
+    }
+  }
+}
+
+use alloc::collections::VecDeque;
 use volatile::Volatile;
 // A structure representing the VGA text buffer.
 #[repr(transparent)]
@@ -56,10 +91,22 @@ pub struct Writer {
   column_position: usize,
   color_code: ColorCode,
   buffer: &'static mut Buffer, //The 'static lifetime specifies that the reference is valid for the whole program run time (which is true for the VGA text buffer).
+  // Every row ever written, oldest first, capped at `HISTORY_ROWS`. The last `BUFFER_HEIGHT`
+  // rows are the live tail; `buffer` always mirrors the `BUFFER_HEIGHT` rows of `history`
+  // starting at `history.len() - BUFFER_HEIGHT - scroll_offset`.
+  history: VecDeque<Row>,
+  // How many rows back from the live tail the visible window currently starts.
+  scroll_offset: usize,
 }
 
 // Printing: now we can use the Writer to modify the buffer's characters. First we create a method to write a single ASCII byte:
 impl Writer {
+  /// Sets the foreground/background colors and blink attribute used for subsequently
+  /// written characters.
+  pub fn set_color(&mut self, foreground: Color, background: Color, blink: bool) {
+    self.color_code = ColorCode::new(foreground, background).with_blink(blink);
+  }
+
   pub fn write_byte(&mut self, byte: u8) {
     match byte {
       b'\n' => self.new_line(),
@@ -69,54 +116,108 @@ impl Writer {
           self.new_line();
         }
 
-        let row = BUFFER_HEIGHT - 1;
         let col = self.column_position;
         let color_code = self.color_code;
-
-        self.buffer.chars[row][col].write(ScreenChar {
+        let screen_char = ScreenChar {
           ascii_character: byte,
           color_code,
-        }); // Instead of a normal assignment using =, we're now using the write method. This guarantees that the compiler will never optimize away this write.
+        };
+
+        self.history.back_mut().expect("history is never empty").chars[col] = screen_char;
+
+        if self.scroll_offset == 0 {
+          self.buffer.chars[BUFFER_HEIGHT - 1][col].write(screen_char); // Instead of a normal assignment using =, we're now using the write method. This guarantees that the compiler will never optimize away this write.
+        }
 
         self.column_position += 1;
+        self.update_cursor();
       }
     }
   }
 
+  // Rather than shifting the 25 visible rows in place and discarding the top one, a new blank
+  // row is appended to the off-screen `history` ring buffer (evicting the oldest row past
+  // `HISTORY_ROWS`) and the visible window is re-blit from it, so scrolled-off lines survive
+  // in `history` for `scroll_up` to bring back.
   fn new_line(&mut self) {
-    for row in 1..BUFFER_HEIGHT {
-      for col in 0..BUFFER_WIDTH {
-        let character = self.buffer.chars[row][col].read();
-        self.buffer.chars[row - 1][col].write(character);
-      }
+    self.history.push_back(Row::blank(self.color_code));
+    if self.history.len() > HISTORY_ROWS {
+      self.history.pop_front();
+    }
+
+    // Keep whatever historical window the user is looking at anchored to the same content
+    // instead of letting it silently scroll as new lines are appended underneath.
+    if self.scroll_offset > 0 {
+      self.scroll_offset = (self.scroll_offset + 1).min(self.history.len() - BUFFER_HEIGHT);
     }
 
-    self.clear_row(BUFFER_HEIGHT - 1);
     self.column_position = 0;
+    self.render_window();
+    self.update_cursor();
+  }
+
+  /// Scrolls the visible window back by `rows`, towards older history.
+  pub fn scroll_up(&mut self, rows: usize) {
+    let max_offset = self.history.len().saturating_sub(BUFFER_HEIGHT);
+    self.scroll_offset = (self.scroll_offset + rows).min(max_offset);
+    self.render_window();
+    self.update_cursor();
+  }
+
+  /// Scrolls the visible window forward by `rows`, back towards the live tail.
+  pub fn scroll_down(&mut self, rows: usize) {
+    self.scroll_offset = self.scroll_offset.saturating_sub(rows);
+    self.render_window();
+    self.update_cursor();
+  }
+
+  // Re-blits a `BUFFER_HEIGHT`-row window of `history` into the hardware buffer at 0xb8000,
+  // starting `scroll_offset` rows before the live tail.
+  fn render_window(&mut self) {
+    let start = self.history.len() - BUFFER_HEIGHT - self.scroll_offset;
+
+    for row in 0..BUFFER_HEIGHT {
+      let source = self.history[start + row];
+      for col in 0..BUFFER_WIDTH {
+        self.buffer.chars[row][col].write(source.chars[col]);
+      }
+    }
   }
 
-  // to clear_row clears a row by overwriting all of its characters with a space character.
-  fn clear_row(&mut self, row: usize) {
-    let blank = ScreenChar {
-      ascii_character: b' ',
-      color_code: self.color_code,
-    };
+  // Writes the hardware cursor's linear position to the CRT controller. Only meaningful
+  // while viewing the live tail, since the cursor marks where the next typed character lands.
+  fn update_cursor(&self) {
+    if self.scroll_offset != 0 {
+      return;
+    }
+
+    use x86_64::instructions::port::Port;
+
+    let position = (BUFFER_HEIGHT - 1) * BUFFER_WIDTH + self.column_position;
 
-    for col in 0..BUFFER_WIDTH {
-      self.buffer.chars[row][col].write(blank);
+    let mut index_port: Port<u8> = Port::new(0x3d4);
+    let mut data_port: Port<u8> = Port::new(0x3d5);
+
+    unsafe {
+      index_port.write(0x0fu8);
+      data_port.write((position & 0xff) as u8);
+      index_port.write(0x0eu8);
+      data_port.write(((position >> 8) & 0xff) as u8);
     }
   }
 
-  // To print whole strings, we can convert them to bytes and print them one-by-one:
+  // To print whole strings, we iterate over chars rather than raw UTF-8 bytes: the VGA text
+  // buffer renders code page 437, not ASCII, so each char is translated through the CP437
+  // lookup table instead of being collapsed to a placeholder whenever it's outside 0x20..=0x7e.
   pub fn write_string(&mut self, s: &str) {
-    for byte in s.bytes() {
-      match byte {
-        // Printable ASCII byte or new line:
-        0x20..=0x7e | b'\n' => self.write_byte(byte),
-
-        // The VGA text buffer only supports ASCII and the additional bytes of code page 437. Rust strings are UTF-8 by default, so they might contain bytes that are not supported by the VGA text buffer.
-        // Not part of printable ASCII range
-        _ => self.write_byte(0xfe), // 0xfe = ■
+    for c in s.chars() {
+      match c {
+        '\n' => self.write_byte(b'\n'),
+        c => match cp437::unicode_to_cp437(c) {
+          Some(byte) => self.write_byte(byte),
+          // Genuinely unrepresentable in CP437.
+          None => self.write_byte(0xfe), // 0xfe = ■
+        },
       }
     }
   }
@@ -153,7 +254,15 @@ lazy_static! {
   pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
     column_position: 0,
     color_code: ColorCode::new(Color::Yellow, Color::Black),
-    buffer: unsafe { &mut *(0xb8000 as *mut Buffer) }
+    buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
+    history: {
+      let mut history = VecDeque::with_capacity(HISTORY_ROWS);
+      for _ in 0..BUFFER_HEIGHT {
+        history.push_back(Row::blank(ColorCode::new(Color::Yellow, Color::Black)));
+      }
+      history
+    },
+    scroll_offset: 0,
   });
 }
 
@@ -209,3 +318,71 @@ fn test_println_output() {
     }
   });
 }
+
+#[test_case]
+fn test_set_color_packs_foreground_background_and_blink() {
+  use x86_64::instructions::interrupts;
+
+  interrupts::without_interrupts(|| {
+    let mut writer = WRITER.lock();
+
+    writer.set_color(Color::Yellow, Color::White, true);
+    let code = writer.color_code;
+    assert_eq!(code.0 & 0x0f, Color::Yellow as u8, "foreground nibble");
+    // White (0xf) is a bright color, so it gets masked to its low 3 bits instead of
+    // bleeding into the blink bit.
+    assert_eq!((code.0 >> 4) & 0x07, (Color::White as u8) & 0x07, "background nibble");
+    assert_eq!(code.0 & 0x80, 0x80, "blink bit set");
+
+    writer.set_color(Color::Black, Color::Black, false);
+    assert_eq!(writer.color_code.0 & 0x80, 0, "blink bit cleared");
+  });
+}
+
+#[test_case]
+fn test_scrollback_survives_eviction_and_scroll_up_down() {
+  use core::fmt::Write;
+  use x86_64::instructions::interrupts;
+
+  interrupts::without_interrupts(|| {
+    let mut writer = WRITER.lock();
+
+    // Push well past HISTORY_ROWS so the ring buffer wraps and evicts its oldest rows.
+    for _ in 0..HISTORY_ROWS + 5 {
+      writeln!(writer, "filler").unwrap();
+    }
+
+    // Write a marker line, then exactly enough filler afterwards to push it one row past
+    // the top of the live-tail window (without scrolling, it should not be visible).
+    writeln!(writer, "ANCHOR").unwrap();
+    for _ in 0..BUFFER_HEIGHT - 1 {
+      writeln!(writer, "filler").unwrap();
+    }
+
+    let row = writer.buffer.chars[0][0].read();
+    assert_ne!(
+      char::from(row.ascii_character),
+      'A',
+      "ANCHOR should be one row past the live-tail window"
+    );
+
+    writer.scroll_up(1);
+    let row = writer.buffer.chars[0][0].read();
+    assert_eq!(
+      char::from(row.ascii_character),
+      'A',
+      "scroll_up(1) should bring ANCHOR back into view at the top row"
+    );
+
+    // Scrolling past either end of history clamps instead of underflowing/overflowing.
+    writer.scroll_up(HISTORY_ROWS * 2);
+    writer.scroll_down(HISTORY_ROWS * 2);
+
+    let row = writer.buffer.chars[0][0].read();
+    assert_ne!(
+      char::from(row.ascii_character),
+      'A',
+      "scrolling back down to the live tail should leave ANCHOR out of view again"
+    );
+  });
+}
@@ -0,0 +1,38 @@
+// Unicode -> code page 437 translation for the VGA text buffer's high range (0x80-0xff).
+// Bytes 0x20-0x7e are plain ASCII and pass through unchanged; this table covers the
+// accented letters, box-drawing glyphs, block elements and currency symbols CP437 adds
+// above that range.
+const CP437_HIGH: [char; 128] = [
+  'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+  'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+  'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+  '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+  '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+  '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+  '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{00a0}',
+];
+
+/// Looks up the CP437 byte for a Unicode scalar value, if CP437 can display it.
+pub(super) fn unicode_to_cp437(c: char) -> Option<u8> {
+  if (0x20..=0x7e).contains(&(c as u32)) {
+    return Some(c as u8);
+  }
+
+  CP437_HIGH
+    .iter()
+    .position(|&cp437_char| cp437_char == c)
+    .map(|index| 0x80 + index as u8)
+}
+
+#[test_case]
+fn test_unicode_to_cp437_known_mappings() {
+  assert_eq!(unicode_to_cp437('Ç'), Some(0x80));
+  assert_eq!(unicode_to_cp437('ó'), Some(0xa2));
+}
+
+#[test_case]
+fn test_unicode_to_cp437_unrepresentable_codepoint() {
+  // An emoji, which CP437 has no glyph for, should fall back to None so write_string can
+  // substitute its own placeholder byte.
+  assert_eq!(unicode_to_cp437('😀'), None);
+}